@@ -4,6 +4,9 @@ use crate::lex::*;
 pub enum Literal {
     Identifier(Token),
     Number(Token),
+    Str(Token),
+    Bool(Token),
+    Nil(Token),
 }
 
 #[derive(Debug)]
@@ -37,6 +40,36 @@ pub struct FunctionDeclaration {
 pub struct If {
     pub test: Expression,
     pub body: Vec<Statement>,
+    pub elseifs: Vec<(Expression, Vec<Statement>)>,
+    pub else_body: Option<Vec<Statement>>,
+}
+
+#[derive(Debug)]
+pub struct While {
+    pub test: Expression,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug)]
+pub struct Repeat {
+    pub body: Vec<Statement>,
+    pub test: Expression,
+}
+
+#[derive(Debug)]
+pub struct NumericFor {
+    pub variable: Token,
+    pub start: Expression,
+    pub stop: Expression,
+    pub step: Option<Expression>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug)]
+pub struct GenericFor {
+    pub variables: Vec<Token>,
+    pub iterators: Vec<Expression>,
+    pub body: Vec<Statement>,
 }
 
 #[derive(Debug)]
@@ -54,6 +87,10 @@ pub struct Return {
 pub enum Statement {
     Expression(Expression),
     If(If),
+    While(While),
+    Repeat(Repeat),
+    NumericFor(NumericFor),
+    GenericFor(GenericFor),
     FunctionDeclaration(FunctionDeclaration),
     Return(Return),
     Local(Local),
@@ -61,306 +98,1044 @@ pub enum Statement {
 
 pub type AST = Vec<Statement>;
 
-fn expect_keyword(tokens: Vec<Token>, index: usize, value: &str) -> bool {
-    if index >= tokens.len() {
-	return false;
-    }
+// A genuine syntax error: a keyword matched but the body that followed it
+// was malformed. Distinct from a non-match, which is modelled as `Ok(None)`
+// so the next alternative in `parse_statement` can be tried.
+#[derive(Debug)]
+pub struct ParseError {
+    pub message: String,
+    pub loc: Location,
+}
+
+// Binding powers for binary operators, keyed by the operator's literal
+// value. The tuple is (left, right) binding power; a higher number binds
+// tighter. Left-associative operators carry a left power one below their
+// right power so that equal-precedence chains fold to the left.
+fn operator_binding_power(value: &str) -> Option<(u8, u8)> {
+    let bp = match value {
+	"==" | "<" | ">" => (1, 2),
+	"+" | "-" => (3, 4),
+	"*" | "/" => (5, 6),
+	_ => return None,
+    };
 
-    let t = tokens[index];
-    return t.kind == TokenKind::Keyword && t.value == value;
+    Some(bp)
 }
 
-fn expect_syntax(tokens: Vec<Token>, index: usize, value: &str) -> bool {
-    if index >= tokens.len() {
-	return false;
+// Holds the token stream and a cursor into it. Parsing advances `idx`
+// through the borrowed slice rather than cloning the token vector at every
+// recursive call. Each parse method moves the cursor itself, through
+// `next()` or by stepping `idx` past a token it has already matched, instead
+// of returning a new index to its caller.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    idx: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(tokens: &'a [Token]) -> Parser<'a> {
+	Parser{tokens: tokens, idx: 0}
     }
 
-    let t = tokens[index];
-    return t.kind == TokenKind::Syntax && t.value == value;
-}
+    fn at_end(&self) -> bool {
+	self.idx >= self.tokens.len()
+    }
 
-fn expect_identifier(tokens: Vec<Token>, index: usize) -> bool {
-    if index >= tokens.len() {
-	return false;
+    fn peek(&self) -> Option<Token> {
+	if self.at_end() {
+	    None
+	} else {
+	    Some(self.tokens[self.idx].clone())
+	}
     }
 
-    let t = tokens[index];
-    return t.kind == TokenKind::Identifier;
-}
+    fn next(&mut self) -> Option<Token> {
+	let token = self.peek();
+	if token.is_some() {
+	    self.idx += 1;
+	}
 
-fn expect_number(tokens: Vec<Token>, index: usize) -> bool {
-    if index >= tokens.len() {
-	return false;
+	token
     }
 
-    let t = tokens[index];
-    return t.kind == TokenKind::Number;
-}
+    // True when the current token matches both `kind` and `value`.
+    fn expect(&self, kind: TokenKind, value: &str) -> bool {
+	match self.peek() {
+	    Some(t) => t.kind == kind && t.value == value,
+	    None => false,
+	}
+    }
 
-fn parse_expression(raw: &Vec<char>, tokens: Vec<Token>, index: usize) -> Option<(Expression, usize)> {
-    if !expect_identifier(tokens, index) || expect_number(tokens, index) {
-	return None;
+    fn expect_keyword(&self, value: &str) -> bool {
+	self.expect(TokenKind::Keyword, value)
     }
 
-    let left = match tokens[index].kind {
-	TokenKind::Number => Expression::Literal(Literal::Number(tokens[index])),
-	TokenKind::Identifier => Expression::Literal(Literal::Identifier(tokens[index])),
-    };
-    let mut next_index = index + 1;
-    if expect_syntax(tokens, next_index, "(") {
-	next_index += 1; // Skip past open paren
-
-	// Function call
-	let mut arguments: Vec<Expression> = vec![];
-	while !expect_syntax(tokens, next_index, ")") {
-	    if arguments.len() > 0 {
-		if !expect_syntax(tokens, next_index, ",") {
-		    println!("{}", tokens[next_index].loc.debug(*raw, "Expected comma between function call arguments:"));
-		    return None;
+    fn expect_syntax(&self, value: &str) -> bool {
+	self.expect(TokenKind::Syntax, value)
+    }
+
+    fn expect_kind(&self, kind: TokenKind) -> bool {
+	match self.peek() {
+	    Some(t) => t.kind == kind,
+	    None => false,
+	}
+    }
+
+    // Builds a hard error anchored at the current token (or the last token
+    // when the cursor has run past the end of the input).
+    fn error(&self, message: &str) -> ParseError {
+	let loc = if self.at_end() {
+	    self.tokens[self.tokens.len() - 1].loc
+	} else {
+	    self.tokens[self.idx].loc
+	};
+
+	ParseError{message: message.to_string(), loc: loc}
+    }
+
+    // Parses a "primary": a literal, an identifier, a function call, or a
+    // parenthesized sub-expression. These are the operands the precedence
+    // climber combines with binary operators.
+    fn parse_primary(&mut self) -> Result<Option<Expression>, ParseError> {
+	// Parenthesized sub-expression: restart the climb at the lowest
+	// binding power, then consume the matching close paren.
+	if self.expect_syntax("(") {
+	    self.idx += 1; // Skip past open paren
+
+	    let expr = match self.parse_expression_bp(0)? {
+		Some(expr) => expr,
+		None => return Err(self.error("Expected valid expression after open parenthesis")),
+	    };
+
+	    if !self.expect_syntax(")") {
+		return Err(self.error("Expected closing parenthesis in expression"));
+	    }
+
+	    self.idx += 1; // Skip past closing paren
+	    return Ok(Some(expr));
+	}
+
+	// String, boolean, and nil literals are primaries in their own right;
+	// unlike identifiers they never carry a function-call suffix.
+	if self.expect_kind(TokenKind::String) {
+	    return Ok(Some(Expression::Literal(Literal::Str(self.next().unwrap()))));
+	}
+
+	if self.expect_keyword("true") || self.expect_keyword("false") {
+	    return Ok(Some(Expression::Literal(Literal::Bool(self.next().unwrap()))));
+	}
+
+	if self.expect_keyword("nil") {
+	    return Ok(Some(Expression::Literal(Literal::Nil(self.next().unwrap()))));
+	}
+
+	if !self.expect_kind(TokenKind::Identifier) && !self.expect_kind(TokenKind::Number) {
+	    return Ok(None);
+	}
+
+	let token = self.next().unwrap();
+	let name = token.clone();
+	let literal = match token.kind {
+	    TokenKind::Number => Expression::Literal(Literal::Number(token)),
+	    _ => Expression::Literal(Literal::Identifier(token)),
+	};
+
+	// An identifier immediately followed by "(" is a function call whose
+	// arguments are themselves arbitrary expressions.
+	if self.expect_syntax("(") {
+	    self.idx += 1; // Skip past open paren
+
+	    let mut arguments: Vec<Expression> = vec![];
+	    while !self.expect_syntax(")") {
+		if arguments.len() > 0 {
+		    if !self.expect_syntax(",") {
+			return Err(self.error("Expected comma between function call arguments"));
+		    }
+
+		    self.idx += 1; // Skip past comma
+		}
+
+		match self.parse_expression_bp(0)? {
+		    Some(arg) => arguments.push(arg),
+		    None => return Err(self.error("Expected valid expression in function call arguments")),
+		}
+	    }
+
+	    self.idx += 1; // Skip past closing paren
+
+	    return Ok(Some(Expression::FunctionCall(FunctionCall{name: name, arguments: arguments})));
+	}
+
+	Ok(Some(literal))
+    }
+
+    // Precedence-climbing (Pratt) core: parse a primary, then fold in every
+    // following binary operator whose left binding power is at least min_bp,
+    // recursing on the right operand with the operator's right binding power.
+    fn parse_expression_bp(&mut self, min_bp: u8) -> Result<Option<Expression>, ParseError> {
+	let mut left = match self.parse_primary()? {
+	    Some(expr) => expr,
+	    None => return Ok(None),
+	};
+
+	while self.expect_kind(TokenKind::Syntax) {
+	    let op = self.peek().unwrap();
+	    let (left_bp, right_bp) = match operator_binding_power(&op.value) {
+		Some(bp) => bp,
+		None => break,
+	    };
+
+	    if left_bp < min_bp {
+		break;
+	    }
+
+	    self.idx += 1; // Skip past operator
+
+	    let right = match self.parse_expression_bp(right_bp)? {
+		Some(expr) => expr,
+		None => return Err(self.error("Expected valid right hand side binary operand")),
+	    };
+	    left = Expression::BinaryOperation(BinaryOperation{left: Box::new(left), right: Box::new(right), operator: op});
+	}
+
+	Ok(Some(left))
+    }
+
+    fn parse_expression(&mut self) -> Result<Option<Expression>, ParseError> {
+	self.parse_expression_bp(0)
+    }
+
+    fn parse_function(&mut self) -> Result<Option<Statement>, ParseError> {
+	if !self.expect_keyword("function") {
+	    return Ok(None);
+	}
+
+	self.idx += 1; // Skip past function
+	if !self.expect_kind(TokenKind::Identifier) {
+	    return Err(self.error("Expected valid identifier for function name"));
+	}
+	let name = self.next().unwrap();
+
+	if !self.expect_syntax("(") {
+	    return Err(self.error("Expected open parenthesis in function declaration"));
+	}
+
+	self.idx += 1; // Skip past open paren
+	let mut parameters: Vec<Token> = vec![];
+	while !self.expect_syntax(")") {
+	    if parameters.len() > 0 {
+		if !self.expect_syntax(",") {
+		    return Err(self.error("Expected comma or close parenthesis after parameter in function declaration"));
 		}
 
-		next_index += 1; // Skip past comma
+		self.idx += 1; // Skip past comma
 	    }
 
-	    let res = parse_expression(raw, tokens, next_index);
-	    if res.is_some() {
-		let (arg, next_next_index) = res.unwrap();
-		next_index = next_next_index;
-		arguments.push(arg);
-	    } else {
-		println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid expression in function call arguments:"));
-		return None;
+	    if !self.expect_kind(TokenKind::Identifier) {
+		return Err(self.error("Expected parameter or close parenthesis in function declaration"));
 	    }
+	    parameters.push(self.next().unwrap());
 	}
 
-	next_index += 1; // Skip past closing paren
+	self.idx += 1; // Skip past close paren
+
+	let mut body: Vec<Statement> = vec![];
+	while !self.expect_keyword("end") {
+	    match self.parse_statement()? {
+		Some(stmt) => body.push(stmt),
+		None => return Err(self.error("Expected valid statement in function declaration")),
+	    }
+	}
 
-	return Some((Expression::FunctionCall(FunctionCall{name: tokens[index], arguments: arguments}), next_index))
+	self.idx += 1; // Skip past end
+
+	Ok(Some(Statement::FunctionDeclaration(FunctionDeclaration{name: name, parameters: parameters, body: body})))
     }
 
-    // Otherwise is a binary operation
-    if next_index >= tokens.len() || tokens[next_index].kind != TokenKind::Syntax {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid binary operation:"));
-	return None;
+    fn parse_return(&mut self) -> Result<Option<Statement>, ParseError> {
+	if !self.expect_keyword("return") {
+	    return Ok(None);
+	}
+
+	self.idx += 1; // Skip past return
+	let expression = match self.parse_expression()? {
+	    Some(expr) => expr,
+	    None => return Err(self.error("Expected valid expression in return statement")),
+	};
+
+	if !self.expect_syntax(";") {
+	    return Err(self.error("Expected semicolon in return statement"));
+	}
+
+	self.idx += 1; // Skip past semicolon
+
+	Ok(Some(Statement::Return(Return{expression: expression})))
     }
 
-    let op = tokens[next_index];
-    next_index += 1; // Skip past op
+    fn parse_local(&mut self) -> Result<Option<Statement>, ParseError> {
+	if !self.expect_keyword("local") {
+	    return Ok(None);
+	}
+
+	self.idx += 1; // Skip past local
+	if !self.expect_kind(TokenKind::Identifier) {
+	    return Err(self.error("Expected valid identifier in local declaration"));
+	}
+
+	let name = self.next().unwrap();
+
+	if !self.expect_syntax("=") {
+	    return Err(self.error("Expected '=' in local declaration"));
+	}
+	self.idx += 1; // Skip past equals
+
+	let expression = match self.parse_expression()? {
+	    Some(expr) => expr,
+	    None => return Err(self.error("Expected valid expression in local declaration")),
+	};
+
+	if !self.expect_syntax(";") {
+	    return Err(self.error("Expected semicolon in local declaration"));
+	}
 
-    if !expect_identifier(tokens, next_index) || !expect_number(tokens, next_index) {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid right hand side binary operand:"));
-	return None;
+	self.idx += 1; // Skip past semicolon
+
+	Ok(Some(Statement::Local(Local{name: name, expression: expression})))
     }
 
-    let right = match tokens[next_index].kind {
-	TokenKind::Number => Expression::Literal(Literal::Number(tokens[next_index])),
-	TokenKind::Identifier => Expression::Literal(Literal::Identifier(tokens[next_index])),
-    };
-    next_index += 1; // Skip past right hand operand
+    // Parses a run of statements up to (but not consuming) the first keyword
+    // in `terminators`. Control-flow bodies share this so each parser only
+    // has to spell out its own opening and closing keywords.
+    fn parse_block(&mut self, terminators: &[&str]) -> Result<Vec<Statement>, ParseError> {
+	let mut statements: Vec<Statement> = vec![];
+	while !self.at_end() && !terminators.iter().any(|t| self.expect_keyword(t)) {
+	    match self.parse_statement()? {
+		Some(stmt) => statements.push(stmt),
+		None => return Err(self.error("Expected valid statement in block")),
+	    }
+	}
 
-    Some((Expression::BinaryOperation(BinaryOperation{left: Box::new(left), right: Box::new(right), operator: op}), next_index))
-}
+	Ok(statements)
+    }
 
-fn parse_function(raw: &Vec<char>, tokens: Vec<Token>, index: usize) -> Option<(Statement, usize)> {
-    if !expect_keyword(tokens, index, "function") {
-	return None;
+    fn parse_if(&mut self) -> Result<Option<Statement>, ParseError> {
+	if !self.expect_keyword("if") {
+	    return Ok(None);
+	}
+
+	self.idx += 1; // Skip past if
+	let test = match self.parse_expression()? {
+	    Some(expr) => expr,
+	    None => return Err(self.error("Expected valid expression for if test")),
+	};
+
+	if !self.expect_keyword("then") {
+	    return Err(self.error("Expected then keyword after if test"));
+	}
+
+	self.idx += 1; // Skip past then
+
+	let body = self.parse_block(&["elseif", "else", "end"])?;
+
+	let mut elseifs: Vec<(Expression, Vec<Statement>)> = vec![];
+	while self.expect_keyword("elseif") {
+	    self.idx += 1; // Skip past elseif
+
+	    let elseif_test = match self.parse_expression()? {
+		Some(expr) => expr,
+		None => return Err(self.error("Expected valid expression for elseif test")),
+	    };
+
+	    if !self.expect_keyword("then") {
+		return Err(self.error("Expected then keyword after elseif test"));
+	    }
+	    self.idx += 1; // Skip past then
+
+	    let elseif_body = self.parse_block(&["elseif", "else", "end"])?;
+	    elseifs.push((elseif_test, elseif_body));
+	}
+
+	let mut else_body = None;
+	if self.expect_keyword("else") {
+	    self.idx += 1; // Skip past else
+	    else_body = Some(self.parse_block(&["end"])?);
+	}
+
+	if !self.expect_keyword("end") {
+	    return Err(self.error("Expected end keyword to close if statement"));
+	}
+
+	self.idx += 1; // Skip past end
+
+	Ok(Some(Statement::If(If{test: test, body: body, elseifs: elseifs, else_body: else_body})))
     }
 
-    let mut next_index = index + 1;
-    if !expect_identifier(tokens, next_index) {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid identifier for function name:"));
-	return None;
+    fn parse_while(&mut self) -> Result<Option<Statement>, ParseError> {
+	if !self.expect_keyword("while") {
+	    return Ok(None);
+	}
+
+	self.idx += 1; // Skip past while
+	let test = match self.parse_expression()? {
+	    Some(expr) => expr,
+	    None => return Err(self.error("Expected valid expression for while test")),
+	};
+
+	if !self.expect_keyword("do") {
+	    return Err(self.error("Expected do keyword in while statement"));
+	}
+
+	self.idx += 1; // Skip past do
+
+	let body = self.parse_block(&["end"])?;
+
+	if !self.expect_keyword("end") {
+	    return Err(self.error("Expected end keyword to close while statement"));
+	}
+
+	self.idx += 1; // Skip past end
+
+	Ok(Some(Statement::While(While{test: test, body: body})))
     }
-    let name = tokens[next_index];
 
-    next_index += 1; // Skip past name
-    if !expect_syntax(tokens, next_index, "(") {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected open parenthesis in function declaration:"));
-	return None;
+    fn parse_repeat(&mut self) -> Result<Option<Statement>, ParseError> {
+	if !self.expect_keyword("repeat") {
+	    return Ok(None);
+	}
+
+	self.idx += 1; // Skip past repeat
+
+	let body = self.parse_block(&["until"])?;
+
+	if !self.expect_keyword("until") {
+	    return Err(self.error("Expected until keyword in repeat statement"));
+	}
+
+	self.idx += 1; // Skip past until
+
+	let test = match self.parse_expression()? {
+	    Some(expr) => expr,
+	    None => return Err(self.error("Expected valid expression for repeat test")),
+	};
+
+	Ok(Some(Statement::Repeat(Repeat{body: body, test: test})))
     }
 
-    next_index += 1; // Skip past open paren
-    let parameters: Vec<Token> = vec![];
-    while !expect_syntax(tokens, next_index, ")") {
-	if parameters.len() > 0 {
-	    if !expect_syntax(tokens, next_index, ",") {
-		println!("{}", tokens[next_index].loc.debug(*raw, "Expected comma or close parenthesis after parameter in function declaration:"));
-		return None;
+    fn parse_for(&mut self) -> Result<Option<Statement>, ParseError> {
+	if !self.expect_keyword("for") {
+	    return Ok(None);
+	}
+
+	self.idx += 1; // Skip past for
+	if !self.expect_kind(TokenKind::Identifier) {
+	    return Err(self.error("Expected valid identifier in for statement"));
+	}
+
+	let first = self.next().unwrap();
+
+	// A single identifier immediately followed by "=" is the numeric form;
+	// anything else is the generic `for vars in exprlist` form.
+	if self.expect_syntax("=") {
+	    self.idx += 1; // Skip past equals
+
+	    let start = match self.parse_expression()? {
+		Some(expr) => expr,
+		None => return Err(self.error("Expected valid expression for numeric for start value")),
+	    };
+
+	    if !self.expect_syntax(",") {
+		return Err(self.error("Expected comma after numeric for start value"));
 	    }
+	    self.idx += 1; // Skip past comma
+
+	    let stop = match self.parse_expression()? {
+		Some(expr) => expr,
+		None => return Err(self.error("Expected valid expression for numeric for stop value")),
+	    };
 
-	    next_index += 1; // Skip past comma
+	    let mut step = None;
+	    if self.expect_syntax(",") {
+		self.idx += 1; // Skip past comma
+
+		step = Some(match self.parse_expression()? {
+		    Some(expr) => expr,
+		    None => return Err(self.error("Expected valid expression for numeric for step value")),
+		});
+	    }
+
+	    if !self.expect_keyword("do") {
+		return Err(self.error("Expected do keyword in for statement"));
+	    }
+	    self.idx += 1; // Skip past do
+
+	    let body = self.parse_block(&["end"])?;
+
+	    if !self.expect_keyword("end") {
+		return Err(self.error("Expected end keyword to close for statement"));
+	    }
+	    self.idx += 1; // Skip past end
+
+	    return Ok(Some(Statement::NumericFor(NumericFor{variable: first, start: start, stop: stop, step: step, body: body})));
 	}
 
-	parameters.push(tokens[next_index]);
+	let mut variables: Vec<Token> = vec![first];
+	while self.expect_syntax(",") {
+	    self.idx += 1; // Skip past comma
+
+	    if !self.expect_kind(TokenKind::Identifier) {
+		return Err(self.error("Expected valid identifier in for statement"));
+	    }
+	    variables.push(self.next().unwrap());
+	}
+
+	if !self.expect_keyword("in") {
+	    return Err(self.error("Expected in keyword in generic for statement"));
+	}
+	self.idx += 1; // Skip past in
+
+	let mut iterators: Vec<Expression> = vec![];
+	loop {
+	    let expr = match self.parse_expression()? {
+		Some(expr) => expr,
+		None => return Err(self.error("Expected valid expression in generic for iterators")),
+	    };
+	    iterators.push(expr);
+
+	    if !self.expect_syntax(",") {
+		break;
+	    }
+	    self.idx += 1; // Skip past comma
+	}
+
+	if !self.expect_keyword("do") {
+	    return Err(self.error("Expected do keyword in for statement"));
+	}
+	self.idx += 1; // Skip past do
+
+	let body = self.parse_block(&["end"])?;
+
+	if !self.expect_keyword("end") {
+	    return Err(self.error("Expected end keyword to close for statement"));
+	}
+	self.idx += 1; // Skip past end
+
+	Ok(Some(Statement::GenericFor(GenericFor{variables: variables, iterators: iterators, body: body})))
     }
 
-    next_index += 1; // Skip past close paren
+    fn parse_expression_statement(&mut self) -> Result<Option<Statement>, ParseError> {
+	let expression = match self.parse_expression()? {
+	    Some(expr) => expr,
+	    None => return Ok(None),
+	};
 
-    let statements: Vec<Statement> = vec![];
-    while !expect_keyword(tokens, next_index, "end") {
-	let res = parse_statement(raw, tokens, next_index);
-	if res.is_some() {
-	    let (stmt, next_next_index) = res.unwrap();
-	    next_index = next_next_index;
-	    statements.push(stmt);
-	} else {
-	    println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid statement in function declaration:"));
-	    return None;
+	if !self.expect_syntax(";") {
+	    return Err(self.error("Expected semicolon after expression"));
 	}
+
+	self.idx += 1; // Skip past semicolon
+
+	Ok(Some(Statement::Expression(expression)))
     }
 
-    next_index += 1; // Skip past end
+    fn parse_statement(&mut self) -> Result<Option<Statement>, ParseError> {
+	// Each alternative leaves `self.idx` untouched when it does not match,
+	// so the next one can be tried from the same position.
+	if let Some(stmt) = self.parse_if()? {
+	    return Ok(Some(stmt));
+	}
+	if let Some(stmt) = self.parse_while()? {
+	    return Ok(Some(stmt));
+	}
+	if let Some(stmt) = self.parse_repeat()? {
+	    return Ok(Some(stmt));
+	}
+	if let Some(stmt) = self.parse_for()? {
+	    return Ok(Some(stmt));
+	}
+	if let Some(stmt) = self.parse_expression_statement()? {
+	    return Ok(Some(stmt));
+	}
+	if let Some(stmt) = self.parse_return()? {
+	    return Ok(Some(stmt));
+	}
+	if let Some(stmt) = self.parse_function()? {
+	    return Ok(Some(stmt));
+	}
+	if let Some(stmt) = self.parse_local()? {
+	    return Ok(Some(stmt));
+	}
 
-    Some((Statement::FunctionDeclaration(FunctionDeclaration{
-	name: name,
-	parameters: parameters,
-	body: statements,
-    }), next_index))
+	Ok(None)
+    }
 }
 
-fn parse_return(raw: &Vec<char>, tokens: Vec<Token>, index: usize) -> Option<(Statement, usize)> {
-    if !expect_keyword(tokens, index, "return") {
-	return None;
+pub fn parse(raw: &Vec<char>, tokens: Vec<Token>) -> Result<AST, String> {
+    let mut parser = Parser::new(&tokens);
+    let mut ast = vec![];
+    while !parser.at_end() {
+	match parser.parse_statement() {
+	    Ok(Some(stmt)) => ast.push(stmt),
+	    Ok(None) => return Err(parser.peek().unwrap().loc.debug(raw, "Invalid token while parsing:")),
+	    Err(err) => return Err(err.loc.debug(raw, &err.message)),
+	}
     }
 
-    let mut next_index = index + 1; // Skip past return
-    let res = parse_expression(raw, tokens, next_index);
-    if !res.is_some() {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid expression in return statement:"));
-	return None;
-    }
+    Ok(ast)
+}
 
-    let (expr, next_next_index) = res.unwrap();
-    next_index = next_next_index;
-    if !expect_syntax(tokens, next_index, ";") {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected semicolon in return statement:"));
-	return None;
+// Parses `tokens` and, when `optimize_ast` is set, runs the constant-folding
+// pass over the result. Callers that want the untouched tree pass `false`.
+pub fn parse_with_options(raw: &Vec<char>, tokens: Vec<Token>, optimize_ast: bool) -> Result<AST, String> {
+    let ast = parse(raw, tokens)?;
+    if optimize_ast {
+	Ok(optimize(ast))
+    } else {
+	Ok(ast)
     }
+}
 
-    next_index += 1; // Skip past semicolon
+// Builds a fresh number literal from a folded value, reusing `loc` so the
+// synthesized token still points at the original source.
+fn number_literal(value: f64, loc: Location) -> Expression {
+    Expression::Literal(Literal::Number(Token{value: value.to_string(), kind: TokenKind::Number, loc: loc}))
+}
 
-    Some((Statement::Return(Return{expression: expr}), next_index))
+fn bool_literal(value: bool, loc: Location) -> Expression {
+    let text = if value { "true" } else { "false" };
+    Expression::Literal(Literal::Bool(Token{value: text.to_string(), kind: TokenKind::Keyword, loc: loc}))
 }
 
-fn parse_local(raw: &Vec<char>, tokens: Vec<Token>, index: usize) -> Option<(Statement, usize)> {
-    if !expect_keyword(tokens, index, "local") {
-	return None;
+// Evaluates a binary operation over two numeric literals at parse time,
+// honoring the operator token's meaning. Returns None when the operands do
+// not parse as numbers, in which case the operation is left intact.
+fn fold_numeric(operator: &Token, left: &Token, right: &Token) -> Option<Expression> {
+    let l: f64 = match left.value.parse() {
+	Ok(v) => v,
+	Err(_) => return None,
+    };
+    let r: f64 = match right.value.parse() {
+	Ok(v) => v,
+	Err(_) => return None,
+    };
+
+    let loc = left.loc;
+    match operator.value.as_str() {
+	"+" => Some(number_literal(l + r, loc)),
+	"-" => Some(number_literal(l - r, loc)),
+	"*" => Some(number_literal(l * r, loc)),
+	"/" => Some(number_literal(l / r, loc)),
+	"==" => Some(bool_literal(l == r, loc)),
+	"<" => Some(bool_literal(l < r, loc)),
+	">" => Some(bool_literal(l > r, loc)),
+	_ => None,
+    }
+}
+
+fn fold_expression(expression: Expression) -> Expression {
+    match expression {
+	Expression::BinaryOperation(op) => {
+	    let left = fold_expression(*op.left);
+	    let right = fold_expression(*op.right);
+
+	    if let (Expression::Literal(Literal::Number(l)), Expression::Literal(Literal::Number(r))) = (&left, &right) {
+		if let Some(folded) = fold_numeric(&op.operator, l, r) {
+		    return folded;
+		}
+	    }
+
+	    Expression::BinaryOperation(BinaryOperation{operator: op.operator, left: Box::new(left), right: Box::new(right)})
+	}
+	Expression::FunctionCall(call) => {
+	    let arguments = call.arguments.into_iter().map(fold_expression).collect();
+	    Expression::FunctionCall(FunctionCall{name: call.name, arguments: arguments})
+	}
+	Expression::Literal(_) => expression,
     }
+}
 
-    let mut next_index = index + 1; // Skip past local
+// A Lua value is truthy unless it is `false` or `nil`; numbers and strings
+// (including zero and the empty string) are always true.
+fn is_constant_true(expression: &Expression) -> bool {
+    match expression {
+	Expression::Literal(Literal::Number(_)) => true,
+	Expression::Literal(Literal::Str(_)) => true,
+	Expression::Literal(Literal::Bool(t)) => t.value == "true",
+	_ => false,
+    }
+}
 
-    if !expect_identifier(tokens, next_index) {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid identifier for function name:"));
-	return None;
+fn is_constant_false(expression: &Expression) -> bool {
+    match expression {
+	Expression::Literal(Literal::Nil(_)) => true,
+	Expression::Literal(Literal::Bool(t)) => t.value == "false",
+	_ => false,
     }
+}
 
-    let name = tokens[next_index];
-    next_index += 1; // Skip past name
+// Folds an if-statement: drops branches with a constant-falsy test, and when
+// a branch's test is constant-true it becomes unconditional and every later
+// branch is unreachable. The result is spliced into the surrounding block, so
+// a fully-resolved if collapses to the statements of its taken branch.
+fn fold_if(iff: If) -> Vec<Statement> {
+    let mut branches: Vec<(Option<Expression>, Vec<Statement>)> = vec![];
+    branches.push((Some(fold_expression(iff.test)), optimize_block(iff.body)));
+    for (test, body) in iff.elseifs {
+	branches.push((Some(fold_expression(test)), optimize_block(body)));
+    }
+    if let Some(body) = iff.else_body {
+	branches.push((None, optimize_block(body)));
+    }
 
-    let res = parse_expression(raw, tokens, next_index);
-    if !res.is_some() {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid expression in local declaration:"));
-	return None;
+    let mut kept: Vec<(Option<Expression>, Vec<Statement>)> = vec![];
+    for (test, body) in branches {
+	match test {
+	    None => kept.push((None, body)),
+	    Some(t) => {
+		if is_constant_true(&t) {
+		    kept.push((None, body));
+		    break;
+		} else if is_constant_false(&t) {
+		    // Unreachable branch, drop it.
+		} else {
+		    kept.push((Some(t), body));
+		}
+	    }
+	}
     }
 
-    let (expr, next_next_index) = res.unwrap();
-    next_index = next_next_index;
+    if kept.is_empty() {
+	return vec![];
+    }
 
-    if !expect_syntax(tokens, next_index, ";") {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected semicolon in return statement:"));
-	return None;
+    // An unconditional leading branch means the if has resolved; inline it.
+    if kept[0].0.is_none() {
+	return kept.into_iter().next().unwrap().1;
     }
 
-    next_index += 1; // Skip past semicolon
+    let mut iter = kept.into_iter();
+    let (first_test, first_body) = iter.next().unwrap();
+    let mut elseifs: Vec<(Expression, Vec<Statement>)> = vec![];
+    let mut else_body = None;
+    for (test, body) in iter {
+	match test {
+	    Some(t) => elseifs.push((t, body)),
+	    None => else_body = Some(body),
+	}
+    }
 
-    Some((Statement::Local(Local{name: name, expression: expr}), next_index))
+    vec![Statement::If(If{test: first_test.unwrap(), body: first_body, elseifs: elseifs, else_body: else_body})]
 }
 
-fn parse_if(raw: &Vec<char>, tokens: Vec<Token>, index: usize) -> Option<(Statement, usize)> {
-    if !expect_keyword(tokens, index, "if") {
-	return None;
+fn fold_statement(statement: Statement) -> Vec<Statement> {
+    match statement {
+	Statement::Expression(e) => vec![Statement::Expression(fold_expression(e))],
+	Statement::Return(r) => vec![Statement::Return(Return{expression: fold_expression(r.expression)})],
+	Statement::Local(l) => vec![Statement::Local(Local{name: l.name, expression: fold_expression(l.expression)})],
+	Statement::FunctionDeclaration(f) => vec![Statement::FunctionDeclaration(FunctionDeclaration{name: f.name, parameters: f.parameters, body: optimize_block(f.body)})],
+	Statement::While(w) => vec![Statement::While(While{test: fold_expression(w.test), body: optimize_block(w.body)})],
+	Statement::Repeat(rp) => vec![Statement::Repeat(Repeat{body: optimize_block(rp.body), test: fold_expression(rp.test)})],
+	Statement::NumericFor(nf) => vec![Statement::NumericFor(NumericFor{variable: nf.variable, start: fold_expression(nf.start), stop: fold_expression(nf.stop), step: nf.step.map(fold_expression), body: optimize_block(nf.body)})],
+	Statement::GenericFor(gf) => vec![Statement::GenericFor(GenericFor{variables: gf.variables, iterators: gf.iterators.into_iter().map(fold_expression).collect(), body: optimize_block(gf.body)})],
+	Statement::If(iff) => fold_if(iff),
     }
+}
 
-    let mut next_index = index + 1; // Skip past if
-    let res = parse_expression(raw, tokens, next_index);
-    if !res.is_some() {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid expression for if test:"));
-	return None;
+fn optimize_block(statements: Vec<Statement>) -> Vec<Statement> {
+    let mut result: Vec<Statement> = vec![];
+    for statement in statements {
+	result.extend(fold_statement(statement));
     }
 
-    let (test, next_next_index) = res.unwrap();
-    next_index = next_next_index;
+    result
+}
+
+// Simplifies the tree before it is consumed: binary operations over two
+// numeric literals are evaluated at parse time, and constant if-conditions
+// are resolved away. Recurses into function, loop, and if bodies as well as
+// call arguments.
+pub fn optimize(ast: AST) -> AST {
+    optimize_block(ast)
+}
 
-    if !expect_keyword(tokens, next_index, "then") {
-	return None;
+// Read-only traversal. The `visit_*` hooks default to no-ops; the `walk_*`
+// drivers below do the structural recursion once so downstream tools (linters,
+// pretty-printers) can override a single hook without re-matching every variant.
+pub trait Visitor {
+    fn visit_statement(&mut self, statement: &Statement) {
+	let _ = statement;
     }
 
-    next_index += 1; // Skip past then
+    fn visit_expression(&mut self, expression: &Expression) {
+	let _ = expression;
+    }
+}
 
-    let statements: Vec<Statement> = vec![];
-    while !expect_keyword(tokens, next_index, "end") {
-	let res = parse_statement(raw, tokens, next_index);
-	if res.is_some() {
-	    let (stmt, next_next_index) = res.unwrap();
-	    next_index = next_next_index;
-	    statements.push(stmt);
-	} else {
-	    println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid statement in if body:"));
-	    return None;
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    visitor.visit_expression(expression);
+    match expression {
+	Expression::BinaryOperation(op) => {
+	    walk_expression(visitor, &op.left);
+	    walk_expression(visitor, &op.right);
+	}
+	Expression::FunctionCall(call) => {
+	    for argument in &call.arguments {
+		walk_expression(visitor, argument);
+	    }
 	}
+	Expression::Literal(_) => {}
     }
+}
 
-    next_index += 1; // Skip past end
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    visitor.visit_statement(statement);
+    match statement {
+	Statement::Expression(e) => walk_expression(visitor, e),
+	Statement::Return(r) => walk_expression(visitor, &r.expression),
+	Statement::Local(l) => walk_expression(visitor, &l.expression),
+	Statement::FunctionDeclaration(f) => walk_block(visitor, &f.body),
+	Statement::While(w) => {
+	    walk_expression(visitor, &w.test);
+	    walk_block(visitor, &w.body);
+	}
+	Statement::Repeat(rp) => {
+	    walk_block(visitor, &rp.body);
+	    walk_expression(visitor, &rp.test);
+	}
+	Statement::NumericFor(nf) => {
+	    walk_expression(visitor, &nf.start);
+	    walk_expression(visitor, &nf.stop);
+	    if let Some(step) = &nf.step {
+		walk_expression(visitor, step);
+	    }
+	    walk_block(visitor, &nf.body);
+	}
+	Statement::GenericFor(gf) => {
+	    for iterator in &gf.iterators {
+		walk_expression(visitor, iterator);
+	    }
+	    walk_block(visitor, &gf.body);
+	}
+	Statement::If(iff) => {
+	    walk_expression(visitor, &iff.test);
+	    walk_block(visitor, &iff.body);
+	    for (test, body) in &iff.elseifs {
+		walk_expression(visitor, test);
+		walk_block(visitor, body);
+	    }
+	    if let Some(body) = &iff.else_body {
+		walk_block(visitor, body);
+	    }
+	}
+    }
+}
 
-    Some((Statement::If(If{test: test, body: statements}), next_index))
+fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, statements: &[Statement]) {
+    for statement in statements {
+	walk_statement(visitor, statement);
+    }
 }
 
-fn parse_expression_statement(raw: &Vec<char>, tokens: Vec<Token>, index: usize) -> Option<(Statement, usize)> {
-    let mut next_index = index;
-    let res = parse_expression(raw, tokens, next_index);
-    if !res.is_some() {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected valid expression in statement:"));
-	return None;
+pub fn walk_ast<V: Visitor + ?Sized>(visitor: &mut V, ast: &AST) {
+    walk_block(visitor, ast);
+}
+
+// Rewriting traversal. Each hook defaults to an identity fold that recurses
+// through `fold_*_default`, so an implementor overriding one node kind still
+// gets the rest of the tree rebuilt for free.
+pub trait Fold {
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+	fold_statement_default(self, statement)
     }
 
-    let (expr, next_next_index) = res.unwrap();
-    next_index = next_next_index;
-    if !expect_syntax(tokens, next_index, ";") {
-	println!("{}", tokens[next_index].loc.debug(*raw, "Expected semicolon after expression:"));
-	return None;
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+	fold_expression_default(self, expression)
     }
+}
 
-    next_index += 1; // Skip past semicolon
+pub fn fold_expression_default<F: Fold + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    match expression {
+	Expression::BinaryOperation(op) => Expression::BinaryOperation(BinaryOperation{
+	    operator: op.operator,
+	    left: Box::new(folder.fold_expression(*op.left)),
+	    right: Box::new(folder.fold_expression(*op.right)),
+	}),
+	Expression::FunctionCall(call) => Expression::FunctionCall(FunctionCall{
+	    name: call.name,
+	    arguments: call.arguments.into_iter().map(|a| folder.fold_expression(a)).collect(),
+	}),
+	Expression::Literal(_) => expression,
+    }
+}
 
-    Some((Statement::Expression(expr), next_index))
+fn fold_block<F: Fold + ?Sized>(folder: &mut F, statements: Vec<Statement>) -> Vec<Statement> {
+    statements.into_iter().map(|s| folder.fold_statement(s)).collect()
 }
 
-fn parse_statement(raw: &Vec<char>, tokens: Vec<Token>, index: usize) -> Option<(Statement, usize)> {
-    let parsers = [parse_if, parse_expression_statement, parse_return, parse_function, parse_local];
-    for parser in parsers {
-	let res = parser(raw, tokens, index);
-	if res.is_some() {
-	    return res;
-	}
+pub fn fold_statement_default<F: Fold + ?Sized>(folder: &mut F, statement: Statement) -> Statement {
+    match statement {
+	Statement::Expression(e) => Statement::Expression(folder.fold_expression(e)),
+	Statement::Return(r) => Statement::Return(Return{expression: folder.fold_expression(r.expression)}),
+	Statement::Local(l) => Statement::Local(Local{name: l.name, expression: folder.fold_expression(l.expression)}),
+	Statement::FunctionDeclaration(f) => Statement::FunctionDeclaration(FunctionDeclaration{name: f.name, parameters: f.parameters, body: fold_block(folder, f.body)}),
+	Statement::While(w) => Statement::While(While{test: folder.fold_expression(w.test), body: fold_block(folder, w.body)}),
+	Statement::Repeat(rp) => Statement::Repeat(Repeat{body: fold_block(folder, rp.body), test: folder.fold_expression(rp.test)}),
+	Statement::NumericFor(nf) => Statement::NumericFor(NumericFor{
+	    variable: nf.variable,
+	    start: folder.fold_expression(nf.start),
+	    stop: folder.fold_expression(nf.stop),
+	    step: nf.step.map(|s| folder.fold_expression(s)),
+	    body: fold_block(folder, nf.body),
+	}),
+	Statement::GenericFor(gf) => Statement::GenericFor(GenericFor{
+	    variables: gf.variables,
+	    iterators: gf.iterators.into_iter().map(|i| folder.fold_expression(i)).collect(),
+	    body: fold_block(folder, gf.body),
+	}),
+	Statement::If(iff) => Statement::If(If{
+	    test: folder.fold_expression(iff.test),
+	    body: fold_block(folder, iff.body),
+	    elseifs: iff.elseifs.into_iter().map(|(t, b)| (folder.fold_expression(t), fold_block(folder, b))).collect(),
+	    else_body: iff.else_body.map(|b| fold_block(folder, b)),
+	}),
+    }
+}
+
+// Two tokens are equal for structural purposes when they share a kind and a
+// value; their source `loc` is deliberately ignored.
+fn token_eq(a: &Token, b: &Token) -> bool {
+    a.kind == b.kind && a.value == b.value
+}
+
+fn tokens_eq(a: &[Token], b: &[Token]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(p, q)| token_eq(p, q))
+}
+
+fn literal_eq(a: &Literal, b: &Literal) -> bool {
+    match (a, b) {
+	(Literal::Identifier(x), Literal::Identifier(y)) => token_eq(x, y),
+	(Literal::Number(x), Literal::Number(y)) => token_eq(x, y),
+	(Literal::Str(x), Literal::Str(y)) => token_eq(x, y),
+	(Literal::Bool(x), Literal::Bool(y)) => token_eq(x, y),
+	(Literal::Nil(x), Literal::Nil(y)) => token_eq(x, y),
+	_ => false,
     }
+}
 
-    None
+fn expression_eq(a: &Expression, b: &Expression) -> bool {
+    match (a, b) {
+	(Expression::Literal(x), Expression::Literal(y)) => literal_eq(x, y),
+	(Expression::FunctionCall(x), Expression::FunctionCall(y)) => token_eq(&x.name, &y.name) && expressions_eq(&x.arguments, &y.arguments),
+	(Expression::BinaryOperation(x), Expression::BinaryOperation(y)) => token_eq(&x.operator, &y.operator) && expression_eq(&x.left, &y.left) && expression_eq(&x.right, &y.right),
+	_ => false,
+    }
 }
 
-pub fn parse(raw: &Vec<char>, tokens: Vec<Token>) -> Result<AST, String> {
-    let ast = vec![];
-    let mut index = 0;
-    while index < tokens.len() {
-	let res = parse_statement(raw, tokens, index);
-	if res.is_some() {
-	    let (stmt, next_index) = res.unwrap();
-	    index = next_index;
-	    ast.push(stmt);
-	    continue;
+fn expressions_eq(a: &[Expression], b: &[Expression]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(p, q)| expression_eq(p, q))
+}
+
+fn option_expression_eq(a: &Option<Expression>, b: &Option<Expression>) -> bool {
+    match (a, b) {
+	(Some(x), Some(y)) => expression_eq(x, y),
+	(None, None) => true,
+	_ => false,
+    }
+}
+
+fn if_eq(a: &If, b: &If) -> bool {
+    if !expression_eq(&a.test, &b.test) || !block_eq(&a.body, &b.body) {
+	return false;
+    }
+
+    if a.elseifs.len() != b.elseifs.len() {
+	return false;
+    }
+    for ((a_test, a_body), (b_test, b_body)) in a.elseifs.iter().zip(&b.elseifs) {
+	if !expression_eq(a_test, b_test) || !block_eq(a_body, b_body) {
+	    return false;
 	}
+    }
 
-	return Err(tokens[index].loc.debug(*raw, "Invalid token while parsing:"));
+    match (&a.else_body, &b.else_body) {
+	(Some(x), Some(y)) => block_eq(x, y),
+	(None, None) => true,
+	_ => false,
     }
+}
 
-    Ok(ast)
+fn statement_eq(a: &Statement, b: &Statement) -> bool {
+    match (a, b) {
+	(Statement::Expression(x), Statement::Expression(y)) => expression_eq(x, y),
+	(Statement::Return(x), Statement::Return(y)) => expression_eq(&x.expression, &y.expression),
+	(Statement::Local(x), Statement::Local(y)) => token_eq(&x.name, &y.name) && expression_eq(&x.expression, &y.expression),
+	(Statement::FunctionDeclaration(x), Statement::FunctionDeclaration(y)) => token_eq(&x.name, &y.name) && tokens_eq(&x.parameters, &y.parameters) && block_eq(&x.body, &y.body),
+	(Statement::While(x), Statement::While(y)) => expression_eq(&x.test, &y.test) && block_eq(&x.body, &y.body),
+	(Statement::Repeat(x), Statement::Repeat(y)) => block_eq(&x.body, &y.body) && expression_eq(&x.test, &y.test),
+	(Statement::NumericFor(x), Statement::NumericFor(y)) => token_eq(&x.variable, &y.variable) && expression_eq(&x.start, &y.start) && expression_eq(&x.stop, &y.stop) && option_expression_eq(&x.step, &y.step) && block_eq(&x.body, &y.body),
+	(Statement::GenericFor(x), Statement::GenericFor(y)) => tokens_eq(&x.variables, &y.variables) && expressions_eq(&x.iterators, &y.iterators) && block_eq(&x.body, &y.body),
+	(Statement::If(x), Statement::If(y)) => if_eq(x, y),
+	_ => false,
+    }
+}
+
+fn block_eq(a: &[Statement], b: &[Statement]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(p, q)| statement_eq(p, q))
+}
+
+// Structurally compares two ASTs while ignoring each token's source location,
+// so parser tests can assert on an expected tree without hardcoding offsets.
+pub fn ast_eq_ignore_loc(a: &AST, b: &AST) -> bool {
+    block_eq(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Lexes and parses `src` to an AST, panicking on any error. Tests assert on
+    // tree shape with `ast_eq_ignore_loc` so source offsets never leak in.
+    fn ast(src: &str) -> AST {
+	let raw: Vec<char> = src.chars().collect();
+	let tokens = lex(&raw).unwrap();
+	parse(&raw, tokens).unwrap()
+    }
+
+    fn optimized(src: &str) -> AST {
+	let raw: Vec<char> = src.chars().collect();
+	let tokens = lex(&raw).unwrap();
+	parse_with_options(&raw, tokens, true).unwrap()
+    }
+
+    #[test]
+    fn precedence_binds_multiplication_tighter() {
+	assert!(ast_eq_ignore_loc(&ast("a + b * c;"), &ast("a + (b * c);")));
+	assert!(!ast_eq_ignore_loc(&ast("a + b * c;"), &ast("(a + b) * c;")));
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+	assert!(ast_eq_ignore_loc(&ast("(a + b) * c;"), &ast("(a + b) * c;")));
+	assert!(!ast_eq_ignore_loc(&ast("(a + b) * c;"), &ast("a + b * c;")));
+    }
+
+    #[test]
+    fn constant_folding_evaluates_arithmetic() {
+	assert!(ast_eq_ignore_loc(&optimized("2 + 3 * 4;"), &ast("14;")));
+    }
+
+    #[test]
+    fn constant_true_if_is_inlined() {
+	assert!(ast_eq_ignore_loc(&optimized("if true then x(); else y(); end"), &ast("x();")));
+    }
+
+    #[test]
+    fn constant_false_if_takes_else() {
+	assert!(ast_eq_ignore_loc(&optimized("if false then x(); else y(); end"), &ast("y();")));
+    }
+
+    #[test]
+    fn optimize_is_identity_without_constants() {
+	let src = "local x = a + b * c;";
+	assert!(ast_eq_ignore_loc(&optimized(src), &ast(src)));
+    }
 }